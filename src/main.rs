@@ -1,6 +1,8 @@
+mod output_management;
 mod wayland;
 
 use clap::{Parser, Subcommand};
+use output_management::{parse_mode, parse_position, parse_transform};
 use wayland::WaylandClient;
 
 #[derive(Parser)]
@@ -27,8 +29,53 @@ enum Command {
         )]
         json: bool,
     },
-    #[command(about = "Wait until an output gets connected or disconnected")]
-    WatchForOutputChanges,
+    #[command(about = "Stream output changes as they happen")]
+    WatchForOutputChanges {
+        #[clap(short, long, help = "Show each event in JSON")]
+        json: bool,
+    },
+    #[command(about = "List the available input seats")]
+    ListSeats {
+        #[clap(short, long, help = "Show the seats in JSON")]
+        json: bool,
+    },
+    #[command(about = "List the globals advertised by the compositor")]
+    ListGlobals {
+        #[clap(short, long, help = "Show the globals in JSON")]
+        json: bool,
+    },
+    #[command(about = "Change an output's mode, scale, position or transform")]
+    ConfigureOutput {
+        #[clap(help = "Name or model of the output to configure")]
+        output: String,
+        #[clap(long, help = "Mode to set, as WxH or WxH@Hz")]
+        mode: Option<String>,
+        #[clap(long, help = "Scale factor to set")]
+        scale: Option<f64>,
+        #[clap(long, help = "Position to set, as X,Y")]
+        position: Option<String>,
+        #[clap(long, help = "Transform to set")]
+        transform: Option<String>,
+        #[clap(long, help = "Enable the output", conflicts_with = "disable")]
+        enable: bool,
+        #[clap(long, help = "Disable the output", conflicts_with = "enable")]
+        disable: bool,
+    },
+}
+
+/// Prints a clean error message and exits with status 1 instead of panicking, mirroring how
+/// `configure_output`'s own errors are reported below.
+trait UnwrapOrExit<T> {
+    fn unwrap_or_exit(self) -> T;
+}
+
+impl<T> UnwrapOrExit<T> for eyre::Result<T> {
+    fn unwrap_or_exit(self) -> T {
+        self.unwrap_or_else(|err| {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        })
+    }
 }
 
 fn main() {
@@ -40,12 +87,55 @@ fn main() {
 
     match args.cmd {
         Command::ListOutputs { short, json } => {
+            let (mut wayland_client, event_queue) = WaylandClient::new().unwrap();
+            wayland_client.list_outputs(event_queue, short, json);
+        }
+        Command::WatchForOutputChanges { json } => {
+            let (mut wayland_client, event_queue) = WaylandClient::new().unwrap();
+            wayland_client.watch_for_output_changes(event_queue, json);
+        }
+        Command::ListSeats { json } => {
+            let (wayland_client, _) = WaylandClient::new().unwrap();
+            wayland_client.list_seats(json);
+        }
+        Command::ListGlobals { json } => {
             let (wayland_client, _) = WaylandClient::new().unwrap();
-            wayland_client.list_outputs(short, json);
+            wayland_client.list_globals(json);
         }
-        Command::WatchForOutputChanges => {
+        Command::ConfigureOutput {
+            output,
+            mode,
+            scale,
+            position,
+            transform,
+            enable,
+            disable,
+        } => {
             let (mut wayland_client, event_queue) = WaylandClient::new().unwrap();
-            wayland_client.watch_for_output_changes(event_queue);
+            let mode = mode.as_deref().map(parse_mode).transpose().unwrap_or_exit();
+            let position = position
+                .as_deref()
+                .map(parse_position)
+                .transpose()
+                .unwrap_or_exit();
+            let transform = transform
+                .as_deref()
+                .map(parse_transform)
+                .transpose()
+                .unwrap_or_exit();
+            if let Err(err) = wayland_client.configure_output(
+                event_queue,
+                &output,
+                mode,
+                scale,
+                position,
+                transform,
+                enable,
+                disable,
+            ) {
+                eprintln!("Error: {err}");
+                std::process::exit(1);
+            }
         }
     }
 }
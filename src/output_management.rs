@@ -0,0 +1,328 @@
+//! Support for changing outputs via the `wlr-output-management-unstable-v1` protocol.
+//!
+//! Listing outputs is handled by SCTK's `OutputState` delegate, but SCTK has no delegate for
+//! *configuring* them, so this module talks to `zwlr_output_manager_v1` and its child objects
+//! directly with plain `wayland-client` `Dispatch` impls.
+
+use eyre::{bail, eyre, Result};
+use log::debug;
+use wayland_client::{
+    globals::GlobalList, protocol::wl_output, Connection, Dispatch, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+use crate::wayland::WaylandClient;
+
+/// A mode advertised by an output [`Head`].
+#[derive(Debug, Clone)]
+pub struct HeadMode {
+    pub mode: ZwlrOutputModeV1,
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate, in mHz, as advertised by the compositor.
+    pub refresh: i32,
+    pub preferred: bool,
+}
+
+/// An output head advertised by the `zwlr_output_manager_v1`.
+#[derive(Debug, Clone)]
+pub struct Head {
+    pub head: ZwlrOutputHeadV1,
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    pub enabled: bool,
+    pub modes: Vec<HeadMode>,
+    pub current_mode: Option<ZwlrOutputModeV1>,
+    pub position: (i32, i32),
+    pub transform: wl_output::Transform,
+    pub scale: f64,
+}
+
+/// The outcome of a [`ZwlrOutputConfigurationV1`] that was committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigurationResult {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// State for the `zwlr_output_manager_v1` global: the heads/modes it has advertised so far, and
+/// the outcome of any configuration submitted through [`WaylandClient::configure_output`].
+#[derive(Debug, Default)]
+pub struct OutputManagementState {
+    manager: Option<ZwlrOutputManagerV1>,
+    heads: Vec<Head>,
+    /// Serial from the last `done` event, required to create a configuration.
+    serial: Option<u32>,
+    configuration_result: Option<ConfigurationResult>,
+}
+
+impl OutputManagementState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the `zwlr_output_manager_v1` global, if the compositor advertises it.
+    pub fn bind(&mut self, globals: &GlobalList, qh: &QueueHandle<WaylandClient>) -> Result<()> {
+        let manager = globals
+            .bind(qh, 1..=4, ())
+            .map_err(|_| eyre!("compositor does not support zwlr_output_manager_v1"))?;
+        self.manager = Some(manager);
+        Ok(())
+    }
+
+    pub fn heads(&self) -> &[Head] {
+        &self.heads
+    }
+
+    pub fn manager(&self) -> Option<&ZwlrOutputManagerV1> {
+        self.manager.as_ref()
+    }
+
+    pub fn serial(&self) -> Option<u32> {
+        self.serial
+    }
+
+    pub fn configuration_result(&self) -> Option<ConfigurationResult> {
+        self.configuration_result
+    }
+
+    fn head_mut(&mut self, head: &ZwlrOutputHeadV1) -> Option<&mut Head> {
+        self.heads.iter_mut().find(|h| &h.head == head)
+    }
+
+    /// Removes a head the compositor has finished with, releasing it and its modes so a
+    /// reconnecting output with the same name/model can't be confused with the stale one.
+    fn remove_head(&mut self, head: &ZwlrOutputHeadV1) {
+        let Some(index) = self.heads.iter().position(|h| &h.head == head) else {
+            return;
+        };
+        let removed = self.heads.remove(index);
+        for mode in removed.modes {
+            mode.mode.release();
+        }
+        removed.head.release();
+    }
+
+    fn mode_mut(&mut self, mode: &ZwlrOutputModeV1) -> Option<&mut HeadMode> {
+        self.heads
+            .iter_mut()
+            .flat_map(|h| h.modes.iter_mut())
+            .find(|m| &m.mode == mode)
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for WaylandClient {
+    fn event(
+        state: &mut Self,
+        _manager: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => {
+                state.output_management.heads.push(Head {
+                    head,
+                    name: String::new(),
+                    description: String::new(),
+                    make: String::new(),
+                    model: String::new(),
+                    enabled: false,
+                    modes: Vec::new(),
+                    current_mode: None,
+                    position: (0, 0),
+                    transform: wl_output::Transform::Normal,
+                    scale: 1.0,
+                });
+            }
+            zwlr_output_manager_v1::Event::Done { serial } => {
+                state.output_management.serial = Some(serial);
+            }
+            zwlr_output_manager_v1::Event::Finished => {
+                debug!("zwlr_output_manager_v1 finished");
+            }
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(WaylandClient, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for WaylandClient {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_head_v1::Event::Finished = event {
+            state.output_management.remove_head(proxy);
+            return;
+        }
+
+        let Some(head) = state.output_management.head_mut(proxy) else {
+            return;
+        };
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => head.name = name,
+            zwlr_output_head_v1::Event::Description { description } => {
+                head.description = description
+            }
+            zwlr_output_head_v1::Event::Make { make } => head.make = make,
+            zwlr_output_head_v1::Event::Model { model } => head.model = model,
+            zwlr_output_head_v1::Event::Enabled { enabled } => head.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::CurrentMode { mode } => head.current_mode = Some(mode),
+            zwlr_output_head_v1::Event::Position { x, y } => head.position = (x, y),
+            zwlr_output_head_v1::Event::Transform { transform } => {
+                if let WEnum::Value(transform) = transform {
+                    head.transform = transform;
+                }
+            }
+            zwlr_output_head_v1::Event::Scale { scale } => head.scale = scale,
+            zwlr_output_head_v1::Event::Mode { mode } => head.modes.push(HeadMode {
+                mode,
+                width: 0,
+                height: 0,
+                refresh: 0,
+                preferred: false,
+            }),
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(WaylandClient, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for WaylandClient {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // The head dispatcher pushes a `HeadMode` as soon as the `mode` event hands it the new
+        // object (see `zwlr_output_head_v1::Event::Mode` above), so by the time any event arrives
+        // here the mode is already attached to its head.
+        let Some(mode) = state.output_management.mode_mut(proxy) else {
+            return;
+        };
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                mode.width = width;
+                mode.height = height;
+            }
+            zwlr_output_mode_v1::Event::Refresh { refresh } => mode.refresh = refresh,
+            zwlr_output_mode_v1::Event::Preferred => mode.preferred = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, ()> for WaylandClient {
+    fn event(
+        state: &mut Self,
+        _configuration: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        state.output_management.configuration_result = Some(match event {
+            zwlr_output_configuration_v1::Event::Succeeded => ConfigurationResult::Succeeded,
+            zwlr_output_configuration_v1::Event::Failed => ConfigurationResult::Failed,
+            zwlr_output_configuration_v1::Event::Cancelled => ConfigurationResult::Cancelled,
+            _ => return,
+        });
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, ()> for WaylandClient {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrOutputConfigurationHeadV1,
+        _event: zwlr_output_configuration_head_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // `zwlr_output_configuration_head_v1` has no events.
+    }
+}
+
+/// A requested mode, either matched against one the head advertised or, failing that, sent as a
+/// custom mode.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestedMode {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in mHz, if specified.
+    pub refresh: Option<i32>,
+}
+
+/// Parses a `--mode` argument of the form `WxH`, or `WxH@Hz`.
+pub fn parse_mode(s: &str) -> Result<RequestedMode> {
+    let (size, refresh) = match s.split_once('@') {
+        Some((size, refresh)) => (size, Some(refresh)),
+        None => (s, None),
+    };
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| eyre!("invalid mode `{s}`, expected WxH or WxH@Hz"))?;
+    let width: i32 = width.parse()?;
+    let height: i32 = height.parse()?;
+    let refresh = refresh
+        .map(|hz| -> Result<i32> {
+            let hz: f64 = hz.parse()?;
+            Ok((hz * 1000.0).round() as i32)
+        })
+        .transpose()?;
+    Ok(RequestedMode {
+        width,
+        height,
+        refresh,
+    })
+}
+
+/// Parses a `--position` argument of the form `X,Y`.
+pub fn parse_position(s: &str) -> Result<(i32, i32)> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| eyre!("invalid position `{s}`, expected X,Y"))?;
+    Ok((x.parse()?, y.parse()?))
+}
+
+/// Parses a `--transform` argument, matching the names `wlr-randr` uses.
+pub fn parse_transform(s: &str) -> Result<wl_output::Transform> {
+    Ok(match s {
+        "normal" => wl_output::Transform::Normal,
+        "90" => wl_output::Transform::_90,
+        "180" => wl_output::Transform::_180,
+        "270" => wl_output::Transform::_270,
+        "flipped" => wl_output::Transform::Flipped,
+        "flipped-90" => wl_output::Transform::Flipped90,
+        "flipped-180" => wl_output::Transform::Flipped180,
+        "flipped-270" => wl_output::Transform::Flipped270,
+        other => bail!(
+            "invalid transform `{other}`, expected one of: normal, 90, 180, 270, flipped, \
+             flipped-90, flipped-180, flipped-270"
+        ),
+    })
+}
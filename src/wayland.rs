@@ -1,17 +1,24 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 
-use eyre::Result;
-use log::warn;
+use eyre::{bail, eyre, Result};
+use log::{debug, warn};
 use smithay_client_toolkit::{
-    delegate_output, delegate_registry,
+    delegate_output, delegate_registry, delegate_seat,
     output::{OutputHandler, OutputInfo, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
+    seat::{Capability, SeatHandler, SeatState},
 };
 use wayland_client::{
-    globals::registry_queue_init, protocol::wl_output, Connection, EventQueue, QueueHandle,
+    backend::ObjectId,
+    globals::registry_queue_init,
+    protocol::{wl_output, wl_seat},
+    Connection, EventQueue, Proxy, QueueHandle,
 };
 
+use crate::output_management::{ConfigurationResult, OutputManagementState, RequestedMode};
+
 /// Application data.
 ///
 /// This type is where the delegates for some parts of the protocol and any application specific data will
@@ -19,7 +26,14 @@ use wayland_client::{
 pub struct WaylandClient {
     registry_state: RegistryState,
     output_state: OutputState,
-    output_changed: bool,
+    output_management: OutputManagementState,
+    seat_state: SeatState,
+    /// Events emitted by the `OutputHandler` callbacks since the last time they were drained,
+    /// consumed by [`WaylandClient::watch_for_output_changes`].
+    output_events: Vec<OutputEvent>,
+    /// Last known state of each output, keyed by object id, so `output_destroyed` can still
+    /// report what was removed.
+    output_cache: HashMap<ObjectId, DisplayOutput>,
 }
 
 impl WaylandClient {
@@ -38,6 +52,9 @@ impl WaylandClient {
         // Initialize the delegate we will use for outputs.
         let output_delegate = OutputState::new(&globals, &qh);
 
+        // Initialize the delegate we will use for seats.
+        let seat_delegate = SeatState::new(&globals, &qh);
+
         // Set up application state.
         //
         // This is where you will store your delegates and any data you wish to access/mutate while the
@@ -45,9 +62,19 @@ impl WaylandClient {
         let mut wayland_client = WaylandClient {
             registry_state,
             output_state: output_delegate,
-            output_changed: false,
+            output_management: OutputManagementState::new(),
+            seat_state: seat_delegate,
+            output_events: Vec::new(),
+            output_cache: HashMap::new(),
         };
 
+        // `zwlr_output_manager_v1` isn't wired up through SCTK's registry handlers (SCTK has no
+        // delegate for it), so bind it ourselves. Not every compositor supports it, so a missing
+        // global is not fatal here: it only becomes an error once a command that needs it runs.
+        if let Err(err) = wayland_client.output_management.bind(&globals, &qh) {
+            debug!("{err}");
+        }
+
         // `OutputState::new()` binds the output globals found in `registry_queue_init()`.
         //
         // After the globals are bound, we need to dispatch again so that events may be sent to the newly
@@ -57,11 +84,36 @@ impl WaylandClient {
         Ok((wayland_client, event_queue))
     }
 
+    /// Looks up the fractional scale reported for `info` via `zwlr_output_manager_v1`, if the
+    /// compositor supports it and has advertised a matching head.
+    fn fractional_scale_for(&self, info: &OutputInfo) -> Option<f64> {
+        let name = info.name.as_deref()?;
+        self.output_management
+            .heads()
+            .iter()
+            .find(|head| head.name == name)
+            .map(|head| head.scale)
+    }
+
+    /// If the compositor supports `zwlr_output_manager_v1`, waits for its initial burst of
+    /// heads/modes to complete (signalled by a `done` event) so that `fractional_scale_for` sees
+    /// a fully populated list rather than racing the server. A no-op when the compositor doesn't
+    /// support the protocol, so commands unrelated to outputs never pay for or wait on this.
+    fn wait_for_output_management(&mut self, event_queue: &mut EventQueue<Self>) {
+        if self.output_management.manager().is_some() {
+            while self.output_management.serial().is_none() {
+                event_queue.blocking_dispatch(self).unwrap();
+            }
+        }
+    }
+
     /// List all outputs. for the connected Wayland server.
-    pub fn list_outputs(&self, short: bool, json: bool) {
+    pub fn list_outputs(&mut self, mut event_queue: EventQueue<Self>, short: bool, json: bool) {
+        self.wait_for_output_management(&mut event_queue);
+
         let outputs = self.output_state.outputs().filter_map(|output| {
             if let Some(info) = &self.output_state.info(&output) {
-                Some(DisplayOutput::new(info))
+                Some(DisplayOutput::new(info, self.fractional_scale_for(info)))
             } else {
                 warn!("No output info found for {:?}", output);
                 None
@@ -83,17 +135,169 @@ impl WaylandClient {
         }
     }
 
-    pub fn watch_for_output_changes(&mut self, mut event_queue: EventQueue<Self>) {
-        // Reset the status here
-        self.output_changed = false;
+    /// Streams output changes forever, printing each `added`/`changed`/`removed` event as it
+    /// happens. With `json` each event is one line; without it an event spans several lines: its
+    /// kind, then the same block `list_outputs` prints for the output. Useful as a backend for
+    /// status bars and hotplug scripts.
+    pub fn watch_for_output_changes(&mut self, mut event_queue: EventQueue<Self>, json: bool) {
+        self.wait_for_output_management(&mut event_queue);
+
         loop {
-            // Dispatch events until the new_output or output_destroyed gets called
-            if self.output_changed {
-                break;
+            // `WaylandClient::new()` already dispatched the outputs connected at startup, so
+            // their `Added` events may already be sitting in `output_events`. Drain those before
+            // blocking on the next dispatch, otherwise a status-bar consumer never sees the
+            // initial output listing.
+            for event in self.output_events.drain(..) {
+                if json {
+                    println!("{}", serde_json::to_string(&event).unwrap());
+                } else {
+                    println!("{event}");
+                }
             }
+
             event_queue.blocking_dispatch(self).unwrap();
         }
     }
+
+    /// List every global advertised by the compositor, interface name, numeric id and the
+    /// version the compositor advertises (not the version this client bound it at, if it bound
+    /// it at all), sorted by interface. Useful to check which optional protocols (e.g.
+    /// `zwlr_output_manager_v1`) a compositor supports.
+    pub fn list_globals(&self, json: bool) {
+        let mut globals: Vec<DisplayGlobal> = self
+            .registry_state
+            .globals()
+            .contents()
+            .with_list(|list| list.iter().map(DisplayGlobal::new).collect());
+        globals.sort_by(|a, b| a.interface.cmp(&b.interface));
+
+        if json {
+            println!("{}", serde_json::to_string(&globals).unwrap());
+        } else {
+            for global in globals {
+                println!("{global}");
+            }
+        }
+    }
+
+    /// List all seats advertised by the connected Wayland server.
+    pub fn list_seats(&self, json: bool) {
+        let seats = self.seat_state.seats().filter_map(|seat| {
+            if let Some(info) = self.seat_state.info(&seat) {
+                Some(DisplaySeat::new(&info))
+            } else {
+                warn!("No seat info found for {:?}", seat);
+                None
+            }
+        });
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(&seats.collect::<Vec<DisplaySeat>>()).unwrap()
+            );
+        } else {
+            for seat in seats {
+                println!("{}", seat);
+            }
+        }
+    }
+
+    /// Changes an output's mode, scale, position, transform and/or enabled state through
+    /// `zwlr_output_manager_v1`.
+    ///
+    /// `output` is matched against each advertised head's name, falling back to its model.
+    #[allow(clippy::too_many_arguments)]
+    pub fn configure_output(
+        &mut self,
+        mut event_queue: EventQueue<Self>,
+        output: &str,
+        mode: Option<RequestedMode>,
+        scale: Option<f64>,
+        position: Option<(i32, i32)>,
+        transform: Option<wl_output::Transform>,
+        enable: bool,
+        disable: bool,
+    ) -> Result<()> {
+        let qh = event_queue.handle();
+
+        if self.output_management.manager().is_none() {
+            bail!("compositor does not support zwlr_output_manager_v1");
+        }
+
+        // Heads are advertised asynchronously after binding the manager, so dispatch until we
+        // have seen a `done` event telling us the initial burst is complete and giving us the
+        // serial we need to create a configuration.
+        while self.output_management.serial().is_none() {
+            event_queue.blocking_dispatch(self)?;
+        }
+
+        let heads = self.output_management.heads();
+        let head = heads
+            .iter()
+            .find(|h| h.name == output)
+            .or_else(|| heads.iter().find(|h| h.model == output))
+            .ok_or_else(|| eyre!("no output named `{output}` found"))?
+            .clone();
+
+        let manager = self
+            .output_management
+            .manager()
+            .ok_or_else(|| eyre!("compositor does not support zwlr_output_manager_v1"))?;
+        let serial = self.output_management.serial().unwrap();
+        let configuration = manager.create_configuration(serial, &qh, ());
+
+        if disable {
+            configuration.disable_head(&head.head);
+        } else {
+            let configuration_head = configuration.enable_head(&head.head, &qh, ());
+
+            if let Some(mode) = mode {
+                if let Some(advertised) = head.modes.iter().find(|m| {
+                    m.width == mode.width
+                        && m.height == mode.height
+                        && mode.refresh.map_or(true, |refresh| refresh == m.refresh)
+                }) {
+                    configuration_head.set_mode(&advertised.mode);
+                } else {
+                    configuration_head.set_custom_mode(
+                        mode.width,
+                        mode.height,
+                        mode.refresh.unwrap_or(0),
+                    );
+                }
+            }
+
+            if let Some(scale) = scale {
+                configuration_head.set_scale(scale);
+            }
+
+            if let Some((x, y)) = position {
+                configuration_head.set_position(x, y);
+            }
+
+            if let Some(transform) = transform {
+                configuration_head.set_transform(transform);
+            }
+
+            // `enable` is the default when a configuration head is created; it only needs to be
+            // accepted explicitly so clippy doesn't flag it as unused.
+            let _ = enable;
+        }
+
+        configuration.commit();
+
+        while self.output_management.configuration_result().is_none() {
+            event_queue.blocking_dispatch(self)?;
+        }
+
+        match self.output_management.configuration_result().unwrap() {
+            ConfigurationResult::Succeeded => Ok(()),
+            ConfigurationResult::Failed => Err(eyre!("compositor rejected output configuration")),
+            ConfigurationResult::Cancelled => Err(eyre!(
+                "output configuration was cancelled by the compositor"
+            )),
+        }
+    }
 }
 
 // In order to use OutputDelegate, we must implement this trait to indicate when something has happened to an
@@ -114,26 +318,49 @@ impl OutputHandler for WaylandClient {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
-        self.output_changed = true;
+        let Some(info) = self.output_state.info(&output) else {
+            warn!("No output info found for {:?}", output);
+            return;
+        };
+        let display_output = DisplayOutput::new(&info, self.fractional_scale_for(&info));
+        self.output_cache
+            .insert(output.id(), display_output.clone());
+        self.output_events.push(OutputEvent::Added {
+            output: display_output,
+        });
     }
 
     fn update_output(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
+        let Some(info) = self.output_state.info(&output) else {
+            warn!("No output info found for {:?}", output);
+            return;
+        };
+        let display_output = DisplayOutput::new(&info, self.fractional_scale_for(&info));
+        self.output_cache
+            .insert(output.id(), display_output.clone());
+        self.output_events.push(OutputEvent::Changed {
+            output: display_output,
+        });
     }
 
     fn output_destroyed(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
-        self.output_changed = true;
+        if let Some(display_output) = self.output_cache.remove(&output.id()) {
+            self.output_events.push(OutputEvent::Removed {
+                output: display_output,
+            });
+        }
     }
 }
 
@@ -141,6 +368,40 @@ impl OutputHandler for WaylandClient {
 // type to the requisite delegate.
 delegate_output!(WaylandClient);
 
+// Seats come and go dynamically just like outputs do, so this mirrors `OutputHandler` above:
+// SCTK calls into these whenever a seat appears, gains/loses a capability, or disappears, and
+// `SeatState` itself keeps track of each seat's name and capabilities for `list_seats` to read.
+impl SeatHandler for WaylandClient {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: wl_seat::WlSeat) {}
+
+    fn new_capability(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _seat: wl_seat::WlSeat,
+        _capability: Capability,
+    ) {
+    }
+
+    fn remove_capability(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _seat: wl_seat::WlSeat,
+        _capability: Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: wl_seat::WlSeat) {
+    }
+}
+
+delegate_seat!(WaylandClient);
+
 // In order for our delegate to know of the existence of globals, we need to implement registry
 // handling for the program. This trait will forward events to the RegistryHandler trait
 // implementations.
@@ -157,13 +418,33 @@ impl ProvidesRegistryState for WaylandClient {
     }
 
     registry_handlers! {
-        // Here we specify that OutputState needs to receive events regarding the creation and destruction of
-        // globals.
+        // Here we specify that OutputState and SeatState need to receive events regarding the creation and
+        // destruction of globals.
         OutputState,
+        SeatState,
     }
 }
 
+/// One `added`/`changed`/`removed` event emitted while watching for output changes.
 #[derive(serde::Serialize)]
+#[serde(tag = "event", content = "output", rename_all = "lowercase")]
+enum OutputEvent {
+    Added { output: DisplayOutput },
+    Changed { output: DisplayOutput },
+    Removed { output: DisplayOutput },
+}
+
+impl Display for OutputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputEvent::Added { output } => write!(f, "added:\n{output}"),
+            OutputEvent::Changed { output } => write!(f, "changed:\n{output}"),
+            OutputEvent::Removed { output } => write!(f, "removed:\n{output}"),
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
 struct DisplayOutput {
     model: String,
     name: Option<String>,
@@ -175,6 +456,8 @@ struct DisplayOutput {
     logical_position: Option<(i32, i32)>,
     logical_size: Option<(i32, i32)>,
     modes: Vec<String>,
+    scale: f64,
+    transform: String,
 }
 
 fn subpixel_to_string(subpixel: wl_output::Subpixel) -> String {
@@ -188,8 +471,27 @@ fn subpixel_to_string(subpixel: wl_output::Subpixel) -> String {
     }
 }
 
+fn transform_to_string(transform: wl_output::Transform) -> String {
+    match transform {
+        wl_output::Transform::Normal => "normal".to_string(),
+        wl_output::Transform::_90 => "90".to_string(),
+        wl_output::Transform::_180 => "180".to_string(),
+        wl_output::Transform::_270 => "270".to_string(),
+        wl_output::Transform::Flipped => "flipped".to_string(),
+        wl_output::Transform::Flipped90 => "flipped-90".to_string(),
+        wl_output::Transform::Flipped180 => "flipped-180".to_string(),
+        wl_output::Transform::Flipped270 => "flipped-270".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
 impl DisplayOutput {
-    pub fn new(info: &OutputInfo) -> Self {
+    /// `fractional_scale` should come from the matching `zwlr_output_head_v1.scale` event when
+    /// the compositor supports `wlr-output-management`, since `wp_fractional_scale_manager_v1`
+    /// reports the scale of a surface rather than of an output and so can't be used to query an
+    /// arbitrary output's fractional scale. When it isn't available, `info.scale_factor` (the
+    /// integer scale every compositor advertises) is used instead.
+    pub fn new(info: &OutputInfo, fractional_scale: Option<f64>) -> Self {
         let model = info.model.clone();
         let name = info.name.clone();
         let description = info.description.clone();
@@ -201,6 +503,8 @@ impl DisplayOutput {
         let logical_position = info.logical_position;
         let logical_size = info.logical_size;
         let modes = info.modes.iter().map(|m| m.to_string()).collect();
+        let scale = fractional_scale.unwrap_or(info.scale_factor as f64);
+        let transform = transform_to_string(info.transform);
         DisplayOutput {
             model,
             name,
@@ -212,10 +516,70 @@ impl DisplayOutput {
             logical_position,
             logical_size,
             modes,
+            scale,
+            transform,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DisplaySeat {
+    name: Option<String>,
+    pointer: bool,
+    keyboard: bool,
+    touch: bool,
+}
+
+impl DisplaySeat {
+    pub fn new(info: &smithay_client_toolkit::seat::SeatInfo) -> Self {
+        DisplaySeat {
+            name: info.name.clone(),
+            pointer: info.has_pointer,
+            keyboard: info.has_keyboard,
+            touch: info.has_touch,
+        }
+    }
+}
+
+/// Prints some [`DisplaySeat`].
+impl Display for DisplaySeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.name.as_deref().unwrap_or("unnamed seat"))?;
+        writeln!(f, "\tpointer: {}", self.pointer)?;
+        writeln!(f, "\tkeyboard: {}", self.keyboard)?;
+        writeln!(f, "\ttouch: {}", self.touch)?;
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DisplayGlobal {
+    interface: String,
+    name: u32,
+    version: u32,
+}
+
+impl DisplayGlobal {
+    pub fn new(global: &wayland_client::globals::Global) -> Self {
+        DisplayGlobal {
+            interface: global.interface.clone(),
+            name: global.name,
+            version: global.version,
         }
     }
 }
 
+/// Prints some [`DisplayGlobal`].
+impl Display for DisplayGlobal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (name {}, version {})",
+            self.interface, self.name, self.version
+        )
+    }
+}
+
 /// Prints some [`DisplayOutput`].
 impl Display for DisplayOutput {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -243,6 +607,8 @@ impl Display for DisplayOutput {
         if let Some((width, height)) = self.logical_size.as_ref() {
             writeln!(f, "\tlogical width: {width}, height: {height}")?;
         }
+        writeln!(f, "\tscale: {}", self.scale)?;
+        writeln!(f, "\ttransform: {}", self.transform)?;
         writeln!(f, "\tmodes:")?;
 
         for mode in &self.modes {